@@ -1,21 +1,31 @@
 use std::{
+    collections::HashMap,
     fs::File,
     io::{self, BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 use std::process;
 
 use clap::{Parser, Subcommand, ValueEnum, ValueHint};
 use md5::Md5;
 use sha1::Sha1;
-use sha2::{Digest, Sha256, Sha512};
-use blake3;
+use sha2::{Digest, Sha256, Sha512, Sha512_256};
+use sha3::{
+    digest::{ExtendableOutput, XofReader},
+    Sha3_256, Sha3_512, Shake128, Shake256,
+};
+use blake2::{
+    digest::{Update, VariableOutput},
+    Blake2bVar,
+};
 use memmap2::Mmap;
 use walkdir::WalkDir;
 use hex::encode;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use globset::{Glob, GlobSetBuilder};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use hmac::{Hmac, Mac};
 
 #[derive(Parser)]
 #[command(
@@ -44,49 +54,208 @@ struct Cli {
     /// Allow using weak algorithms (md5, sha1)
     #[arg(long)]
     allow_weak: bool,
+
+    /// Authenticate the checksum with a secret key: hex bytes, or `@path` to
+    /// read hex from a file. BLAKE3 uses its native keyed mode; the SHA
+    /// family is wrapped in HMAC. Not supported with blake2b, shake128,
+    /// shake256, crc32 or xxh3.
+    #[arg(long, value_name = "HEX|@FILE")]
+    key: Option<String>,
+
+    /// Derive a context-separated BLAKE3 subkey (via `blake3::derive_key`)
+    /// from `--key` before hashing, instead of using `--key` directly
+    #[arg(long, value_name = "CONTEXT")]
+    derive_key: Option<String>,
+
+    /// Request a non-default digest size, in bytes. BLAKE3 accepts any
+    /// length via its extendable-output function; BLAKE2b accepts 1-64.
+    /// Not supported by the fixed-width algorithms.
+    #[arg(long, value_name = "BYTES")]
+    length: Option<usize>,
+}
+
+/// Map a BSD-style tag (`SHA256`, `MD5`, `BLAKE2b-256`, ...) to the algorithm
+/// it names. `SHA3-256`/`SHA3-512`/`SHA512-256` are matched as whole tags
+/// (their own names contain a hyphen); `BLAKE3`/`BLAKE2b`/`SHAKE128`/`SHAKE256`
+/// additionally accept a trailing `-BITS` length suffix, whose value is discarded here since the
+/// actual output length is reconstructed from the hex digest itself.
+fn bsd_tag_to_algo(tag: &str) -> Option<HashAlgo> {
+    match tag {
+        "MD5" => Some(HashAlgo::Md5),
+        "SHA1" => Some(HashAlgo::Sha1),
+        "SHA256" => Some(HashAlgo::Sha256),
+        "SHA512" => Some(HashAlgo::Sha512),
+        "SHA3-256" => Some(HashAlgo::Sha3_256),
+        "SHA3-512" => Some(HashAlgo::Sha3_512),
+        "SHA512-256" => Some(HashAlgo::Sha512_256),
+        "BLAKE3" => Some(HashAlgo::Blake3),
+        "BLAKE2b" => Some(HashAlgo::Blake2b),
+        "SHAKE128" => Some(HashAlgo::Shake128),
+        "SHAKE256" => Some(HashAlgo::Shake256),
+        "CRC32" => Some(HashAlgo::Crc32),
+        "XXH3" => Some(HashAlgo::Xxh3),
+        _ => {
+            if let Some(bits) = tag.strip_prefix("BLAKE3-") { if bits.chars().all(|c| c.is_ascii_digit()) { return Some(HashAlgo::Blake3); } }
+            if let Some(bits) = tag.strip_prefix("BLAKE2b-") { if bits.chars().all(|c| c.is_ascii_digit()) { return Some(HashAlgo::Blake2b); } }
+            if let Some(bits) = tag.strip_prefix("SHAKE128-") { if bits.chars().all(|c| c.is_ascii_digit()) { return Some(HashAlgo::Shake128); } }
+            if let Some(bits) = tag.strip_prefix("SHAKE256-") { if bits.chars().all(|c| c.is_ascii_digit()) { return Some(HashAlgo::Shake256); } }
+            None
+        }
+    }
+}
+
+/// Render a BSD-style tag for an algorithm, inverting `bsd_tag_to_algo`.
+/// Variable-length algorithms get a `-BITS` suffix when `length` (bytes) is
+/// known, e.g. `BLAKE2b-256`, matching coreutils' `b2sum --tag` output.
+fn algo_to_bsd_tag(algo: &HashAlgo, length: Option<usize>) -> String {
+    let base = match algo {
+        HashAlgo::Md5 => "MD5",
+        HashAlgo::Sha1 => "SHA1",
+        HashAlgo::Sha256 => "SHA256",
+        HashAlgo::Sha512 => "SHA512",
+        HashAlgo::Sha3_256 => "SHA3-256",
+        HashAlgo::Sha3_512 => "SHA3-512",
+        HashAlgo::Sha512_256 => "SHA512-256",
+        HashAlgo::Blake3 => "BLAKE3",
+        HashAlgo::Blake2b => "BLAKE2b",
+        HashAlgo::Crc32 => "CRC32",
+        HashAlgo::Xxh3 => "XXH3",
+        HashAlgo::Shake128 => "SHAKE128",
+        HashAlgo::Shake256 => "SHAKE256",
+    };
+    match (algo, length) {
+        (HashAlgo::Blake3 | HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256, Some(n)) => format!("{}-{}", base, n * 8),
+        _ => base.to_string(),
+    }
+}
+
+/// Parse a BSD-tagged coreutils line: `SHA256 (path) = hexhash`.
+fn parse_bsd_line(line: &str) -> Option<(String, String, String)> {
+    let open = line.find(" (")?;
+    let tag = &line[..open];
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') { return None; }
+    let rest = &line[open + 2..];
+    let close = rest.rfind(") = ")?;
+    let path = &rest[..close];
+    let hash = &rest[close + 4..];
+    if path.is_empty() || hash.is_empty() || !is_valid_hex(hash) { return None; }
+    Some((tag.to_string(), path.to_string(), hash.to_string()))
+}
+
+/// Parse a GNU coreutils line: `hexhash␣␣path` (text mode) or `hexhash *path` (binary mode).
+fn parse_gnu_line(line: &str) -> Option<(String, String)> {
+    let sp = line.find(char::is_whitespace)?;
+    let hash = &line[..sp];
+    if hash.is_empty() || !is_valid_hex(hash) { return None; }
+    let rest = &line[sp..];
+    let path = rest.strip_prefix(" *").or_else(|| rest.strip_prefix("  ")).or_else(|| rest.strip_prefix(' '))?;
+    if path.is_empty() { return None; }
+    Some((hash.to_string(), path.to_string()))
+}
+
+/// Infer an algorithm from a GNU-format hex digest length, breaking the
+/// 64-char (SHA-256/BLAKE3/BLAKE2b/SHA3-256/SHA-512-256/SHAKE128) and 128-char
+/// (SHA-512/SHA3-512/SHAKE256/BLAKE2b) ties with `hint` (from `--algo`) when
+/// one is given; without a matching hint they default to SHA-256/SHA-512.
+fn algo_from_hex_len(len: usize, hint: Option<&HashAlgo>) -> Option<HashAlgo> {
+    match len {
+        32 => Some(HashAlgo::Md5),
+        40 => Some(HashAlgo::Sha1),
+        64 => match hint {
+            Some(HashAlgo::Blake3) => Some(HashAlgo::Blake3),
+            Some(HashAlgo::Blake2b) => Some(HashAlgo::Blake2b),
+            Some(HashAlgo::Sha3_256) => Some(HashAlgo::Sha3_256),
+            Some(HashAlgo::Sha512_256) => Some(HashAlgo::Sha512_256),
+            Some(HashAlgo::Shake128) => Some(HashAlgo::Shake128),
+            _ => Some(HashAlgo::Sha256),
+        },
+        128 => match hint {
+            Some(HashAlgo::Sha3_512) => Some(HashAlgo::Sha3_512),
+            Some(HashAlgo::Shake256) => Some(HashAlgo::Shake256),
+            Some(HashAlgo::Blake2b) => Some(HashAlgo::Blake2b),
+            _ => Some(HashAlgo::Sha512),
+        },
+        _ => None,
+    }
+}
+
+/// Key and variable-length knobs accepted by `hasher()`.
+#[derive(Clone, Copy, Default)]
+struct HashOpts<'a> {
+    key: Option<&'a [u8]>,
+    length: Option<usize>,
+}
+
+/// Path-resolution and reporting knobs for `verify_sumfile`/`verify_hash_file`.
+#[derive(Clone, Copy)]
+struct VerifyOpts<'a> {
+    base_dir: Option<&'a Path>,
+    allow_absolute: bool,
+    quiet: bool,
+    status: bool,
+    strict: bool,
+    allow_weak: bool,
 }
 
-fn verify_sumfile(checksum_file: &Path, algo: &HashAlgo, base_dir: Option<&Path>, allow_absolute: bool, quiet: bool) -> io::Result<i32> {
+/// Verify a GNU/BSD-style coreutils checksum file (`md5sum`, `sha256sum`, ...),
+/// auto-detecting the algorithm per line when `algo_hint` is `None`. For the
+/// variable-length algorithms (BLAKE3, BLAKE2b, SHAKE128, SHAKE256) the output length is
+/// reconstructed from the parsed hex digest's own length.
+fn verify_sumfile(checksum_file: &Path, algo_hint: Option<&HashAlgo>, opts: HashOpts, verify_opts: VerifyOpts) -> io::Result<i32> {
+    let VerifyOpts { base_dir, allow_absolute, quiet, status, strict, allow_weak } = verify_opts;
     let file = File::open(checksum_file)?;
     let reader = BufReader::new(file);
     let mut ok = 0usize;
     let mut failed = 0usize;
     let mut missing = 0usize;
     let mut invalid_path = 0usize;
+    // Unparseable lines (bad tag, unrecognized hash length, garbled
+    // syntax): only a failure under --strict, same as coreutils' `--strict`.
+    let mut malformed = 0usize;
+    // Real hashing/IO failures (unreadable file, incompatible --key/algo,
+    // bad base dir): always a failure, regardless of --strict.
     let mut errors = 0usize;
     for line in reader.lines() {
         match line {
             Ok(l) => {
                 let trimmed = l.trim_end();
                 if trimmed.is_empty() { continue; }
-                let mut parts = trimmed.splitn(2, |c: char| c.is_whitespace());
-                let hash = parts.next().unwrap_or("");
-                let rest = parts.next().unwrap_or("").trim_start();
-                if hash.is_empty() || rest.is_empty() { errors += 1; if !quiet { eprintln!("❌ invalid sumfile line: {}", trimmed); } continue; }
-                let record = HashResult { path: rest.to_string(), hash: hash.to_string() };
-                let raw_path = Path::new(&record.path);
-                if !allow_absolute && raw_path.is_absolute() { invalid_path += 1; if !quiet { eprintln!("❌ absolute path not allowed: {}", raw_path.display()); } continue; }
+                let (algo, path, hash) = if let Some((tag, path, hash)) = parse_bsd_line(trimmed) {
+                    let Some(algo) = bsd_tag_to_algo(&tag) else { malformed += 1; if !status { eprintln!("❌ unsupported algorithm tag '{}': {}", tag, trimmed); } continue; };
+                    (algo, path, hash)
+                } else if let Some((hash, path)) = parse_gnu_line(trimmed) {
+                    let Some(algo) = algo_from_hex_len(hash.len(), algo_hint) else { malformed += 1; if !status { eprintln!("❌ cannot infer algorithm from hash length ({} hex chars): {}", hash.len(), trimmed); } continue; };
+                    (algo, path, hash)
+                } else { malformed += 1; if !status { eprintln!("❌ invalid sumfile line: {}", trimmed); } continue; };
+                if is_weak_algo(&algo) && !allow_weak { errors += 1; if !status { eprintln!("❌ refusing weak algorithm {:?} (pass --allow-weak to proceed): {}", algo, trimmed); } continue; }
+                let raw_path = Path::new(&path);
+                if !allow_absolute && raw_path.is_absolute() { invalid_path += 1; if !status { eprintln!("❌ absolute path not allowed: {}", raw_path.display()); } continue; }
                 let resolved = if let Some(base) = base_dir { if raw_path.is_absolute() { raw_path.to_path_buf() } else { base.join(raw_path) } } else { raw_path.to_path_buf() };
                 if let Some(base) = base_dir {
-                    let Ok(base_can) = base.canonicalize() else { errors += 1; if !quiet { eprintln!("❌ cannot canonicalize base dir: {}", base.display()); } continue; };
-                    if let Ok(res_can) = resolved.canonicalize() { if !res_can.starts_with(&base_can) { invalid_path += 1; if !quiet { eprintln!("❌ path escapes base dir: {}", resolved.display()); } continue; } }
+                    let Ok(base_can) = base.canonicalize() else { errors += 1; if !status { eprintln!("❌ cannot canonicalize base dir: {}", base.display()); } continue; };
+                    if let Ok(res_can) = resolved.canonicalize() { if !res_can.starts_with(&base_can) { invalid_path += 1; if !status { eprintln!("❌ path escapes base dir: {}", resolved.display()); } continue; } }
                 }
                 if resolved.exists() {
-                    match hash_file(&resolved, algo) {
+                    let line_opts = HashOpts { key: opts.key, length: if matches!(algo, HashAlgo::Blake3 | HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256) { Some(hash.len() / 2) } else { None } };
+                    match hash_file(&resolved, &algo, line_opts) {
                         Ok(h) => {
-                            let ok_cmp = constant_time_eq(h.as_bytes(), record.hash.as_bytes());
-                            if ok_cmp { ok += 1; if !quiet { println!("✅ {} OK", resolved.display()); } }
-                            else { failed += 1; println!("❌ {} FAILED", resolved.display()); }
+                            let ok_cmp = constant_time_eq(h.as_bytes(), hash.as_bytes());
+                            if ok_cmp { ok += 1; if !status && !quiet { println!("✅ {} OK", resolved.display()); } }
+                            else { failed += 1; if !status { println!("❌ {} FAILED", resolved.display()); } }
                         }
-                        Err(e) => { errors += 1; eprintln!("❌ {} ERROR: {}", resolved.display(), e); }
+                        Err(e) => { errors += 1; if !status { eprintln!("❌ {} ERROR: {}", resolved.display(), e); } }
                     }
-                } else { missing += 1; println!("⚠️ {} MISSING", resolved.display()); }
+                } else { missing += 1; if !status { println!("⚠️ {} MISSING", resolved.display()); } }
             }
-            Err(e) => { errors += 1; eprintln!("❌ read error: {}", e); }
+            Err(e) => { errors += 1; if !status { eprintln!("❌ read error: {}", e); } }
         }
     }
-    if !quiet { eprintln!("Summary: OK={} FAILED={} MISSING={} INVALID_PATH={} ERROR={}", ok, failed, missing, invalid_path, errors); }
-    Ok(if failed == 0 && missing == 0 && invalid_path == 0 && errors == 0 { 0 } else { 1 })
+    if !status && !quiet {
+        eprintln!("Summary: OK={} FAILED={} MISSING={} INVALID_PATH={} ERROR={}", ok, failed, missing, invalid_path, errors + malformed);
+        println!("{} OK, {} failed", ok, failed + missing + invalid_path + errors + if strict { malformed } else { 0 });
+    }
+    let malformed_failure = strict && malformed > 0;
+    Ok(if failed == 0 && missing == 0 && invalid_path == 0 && errors == 0 && !malformed_failure { 0 } else { 1 })
 }
 
 #[derive(Serialize, Deserialize)]
@@ -109,6 +278,18 @@ enum Commands {
         progress: bool,
         #[arg(long)]
         archives: bool,
+        /// Split each file into fixed-size pieces and emit one hash per piece
+        /// (`path#piece=K  hash`) alongside the whole-file hash
+        #[arg(long, value_name = "BYTES")]
+        pieces: Option<u64>,
+        /// Emit BSD-tagged lines (`ALGO (path) = hash`) instead of GNU-style
+        /// `hash  path`, e.g. for consumption by `sha256sum -c`/`verify --sumfile`
+        #[arg(long)]
+        tag: bool,
+        /// Persist computed digests to this file, keyed by path/size/mtime, and
+        /// reuse them on later runs instead of re-reading unchanged files
+        #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+        cache: Option<PathBuf>,
     },
     #[command(visible_aliases = ["cmp"])]
     Compare {
@@ -123,67 +304,506 @@ enum Commands {
     Verify {
         #[arg(value_name = "CSV", value_hint = ValueHint::FilePath)]
         checksum_file: PathBuf,
-        #[arg(long, value_enum, default_value = "sha256")]
-        algo: HashAlgo,
+        /// Algorithm to verify against. For --sumfile this is only a hint used
+        /// to resolve the 64- and 128-hex-char ambiguities of GNU lines (e.g.
+        /// SHA-256 vs BLAKE3); omit to auto-detect per line from the BSD tag
+        /// or GNU hash length.
+        #[arg(long, value_enum)]
+        algo: Option<HashAlgo>,
         #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
         base_dir: Option<PathBuf>,
         #[arg(long)]
         allow_absolute: bool,
         #[arg(long)]
         sumfile: bool,
+        /// Exit-code only: suppress all OK/FAILED/MISSING output and the summary
+        #[arg(long)]
+        status: bool,
+        /// Treat malformed or unparseable lines as a verification failure
+        #[arg(long)]
+        strict: bool,
+        /// Piece size used by `generate --pieces`; when a whole-file hash fails
+        /// to verify, re-hash each piece and report exactly which ones differ
+        #[arg(long, value_name = "BYTES")]
+        pieces: Option<u64>,
+    },
+    /// Produce one deterministic hash for an entire directory tree
+    #[command(visible_aliases = ["tree"])]
+    Digest {
+        #[arg(value_name = "PATH", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value = "sha256")]
+        algo: HashAlgo,
+        #[arg(long, value_name = "GLOB", num_args = 1..)]
+        exclude: Vec<String>,
+        /// Skip dotfiles and dotdirs
+        #[arg(long)]
+        ignore_hidden: bool,
+        /// Follow symlinks while walking the tree
+        #[arg(long)]
+        follow_symlinks: bool,
+    },
+    #[command(visible_aliases = ["dup"])]
+    Dedup {
+        #[arg(value_name = "PATH", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value = "sha256")]
+        algo: HashAlgo,
+        #[arg(long, value_name = "GLOB", num_args = 1..)]
+        exclude: Vec<String>,
+        /// Bytes read from the start of each file for the cheap stage-2 prefilter
+        /// that splits large same-size buckets before a full hash
+        #[arg(long, default_value_t = 16384)]
+        partial_bytes: u64,
+        /// Skip files smaller than this many bytes
+        #[arg(long, default_value_t = 0)]
+        min_size: u64,
     },
 }
 
 #[derive(Clone, ValueEnum, Debug)]
-enum HashAlgo { Md5, Sha1, Sha256, Sha512, Blake3 }
+enum HashAlgo {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    #[value(name = "sha3-512")]
+    Sha3_512,
+    #[value(name = "sha512-256")]
+    Sha512_256,
+    Blake3,
+    Blake2b,
+    Crc32,
+    Xxh3,
+    /// SHAKE128 XOF, 32-byte (256-bit) output by default; use `--length` for more
+    Shake128,
+    /// SHAKE256 XOF, 64-byte (512-bit) output by default; use `--length` for more
+    Shake256,
+}
 
 #[derive(Clone, ValueEnum)]
 enum OutputFormat { Text, Json, Csv, Sumfile }
 
-fn hash_bytes(data: &[u8], algo: &HashAlgo) -> String {
-    match algo {
-        HashAlgo::Md5 => encode(Md5::digest(data)),
-        HashAlgo::Sha1 => encode(Sha1::digest(data)),
-        HashAlgo::Sha256 => encode(Sha256::digest(data)),
-        HashAlgo::Sha512 => encode(Sha512::digest(data)),
-        HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+/// One streaming implementation shared by every algorithm so `hash_bytes` and
+/// `hash_reader` don't each need their own five-arm `match`.
+trait StreamHasher {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> String;
+}
+
+struct Md5Hasher(Md5);
+impl StreamHasher for Md5Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha1Hasher(Sha1);
+impl StreamHasher for Sha1Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha256Hasher(Sha256);
+impl StreamHasher for Sha256Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha512Hasher(Sha512);
+impl StreamHasher for Sha512Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha3_256Hasher(Sha3_256);
+impl StreamHasher for Sha3_256Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha3_512Hasher(Sha3_512);
+impl StreamHasher for Sha3_512Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+struct Sha512_256Hasher(Sha512_256);
+impl StreamHasher for Sha512_256Hasher {
+    fn update(&mut self, data: &[u8]) { Digest::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize()) }
+}
+
+/// BLAKE3, always driven through its extendable-output function so a plain
+/// `--length` request and the default 32-byte digest share one code path.
+struct Blake3HasherImpl(blake3::Hasher, usize);
+impl StreamHasher for Blake3HasherImpl {
+    fn update(&mut self, data: &[u8]) { self.0.update(data); }
+    fn finalize(self: Box<Self>) -> String {
+        let mut out = vec![0u8; self.1];
+        self.0.finalize_xof().fill(&mut out);
+        encode(out)
+    }
+}
+
+struct Crc32Hasher(crc32fast::Hasher);
+impl StreamHasher for Crc32Hasher {
+    fn update(&mut self, data: &[u8]) { self.0.update(data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:08x}", self.0.finalize()) }
+}
+
+struct Xxh3HasherImpl(xxhash_rust::xxh3::Xxh3);
+impl StreamHasher for Xxh3HasherImpl {
+    fn update(&mut self, data: &[u8]) { self.0.update(data); }
+    fn finalize(self: Box<Self>) -> String { format!("{:016x}", self.0.digest()) }
+}
+
+/// BLAKE2b with a caller-chosen output length (1-64 bytes), via `Blake2bVar`.
+struct Blake2bHasherImpl(Blake2bVar);
+impl StreamHasher for Blake2bHasherImpl {
+    fn update(&mut self, data: &[u8]) { Update::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String {
+        let mut out = vec![0u8; self.0.output_size()];
+        self.0.finalize_variable(&mut out).expect("output buffer matches configured size");
+        encode(out)
+    }
+}
+
+/// SHAKE128, driven through its extendable-output function so the default
+/// 32-byte digest and a `--length` request share one code path.
+struct Shake128HasherImpl(Shake128, usize);
+impl StreamHasher for Shake128HasherImpl {
+    fn update(&mut self, data: &[u8]) { Update::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String {
+        let mut out = vec![0u8; self.1];
+        XofReader::read(&mut self.0.finalize_xof(), &mut out);
+        encode(out)
+    }
+}
+
+/// SHAKE256, driven through its extendable-output function so the default
+/// 64-byte digest and a `--length` request share one code path.
+struct Shake256HasherImpl(Shake256, usize);
+impl StreamHasher for Shake256HasherImpl {
+    fn update(&mut self, data: &[u8]) { Update::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String {
+        let mut out = vec![0u8; self.1];
+        XofReader::read(&mut self.0.finalize_xof(), &mut out);
+        encode(out)
+    }
+}
+
+/// HMAC wraps any of the SHA-family digests to provide keyed/authenticated
+/// checksums; one impl covers all of them via the `Mac` trait.
+struct HmacStreamHasher<D: Mac>(D);
+impl<D: Mac> StreamHasher for HmacStreamHasher<D> {
+    fn update(&mut self, data: &[u8]) { Mac::update(&mut self.0, data); }
+    fn finalize(self: Box<Self>) -> String { encode(self.0.finalize().into_bytes()) }
+}
+
+/// Construct the boxed streaming hasher for an algorithm, optionally keyed
+/// and/or at a non-default output length. BLAKE3 uses its native keyed mode
+/// (32-byte key) and XOF for length; BLAKE2b and the SHAKE128/256 XOFs
+/// support length natively but not yet keying; the SHA family is wrapped in
+/// HMAC. CRC32/XXH3 aren't cryptographic and reject keys outright. The
+/// fixed-width algorithms ignore `opts.length`; `resolve_length` is what
+/// rejects `--length` for them.
+fn hasher(algo: &HashAlgo, opts: HashOpts) -> io::Result<Box<dyn StreamHasher>> {
+    fn hmac_err(e: impl std::fmt::Display) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, format!("invalid HMAC key: {}", e)) }
+    let key = opts.key;
+    Ok(match (algo, key) {
+        (HashAlgo::Md5, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Md5>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Md5, None) => Box::new(Md5Hasher(Md5::new())),
+        (HashAlgo::Sha1, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha1>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha1, None) => Box::new(Sha1Hasher(Sha1::new())),
+        (HashAlgo::Sha256, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha256>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha256, None) => Box::new(Sha256Hasher(Sha256::new())),
+        (HashAlgo::Sha512, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha512>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha512, None) => Box::new(Sha512Hasher(Sha512::new())),
+        (HashAlgo::Sha3_256, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha3_256>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha3_256, None) => Box::new(Sha3_256Hasher(Sha3_256::new())),
+        (HashAlgo::Sha3_512, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha3_512>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha3_512, None) => Box::new(Sha3_512Hasher(Sha3_512::new())),
+        (HashAlgo::Sha512_256, Some(k)) => Box::new(HmacStreamHasher(Hmac::<Sha512_256>::new_from_slice(k).map_err(hmac_err)?)),
+        (HashAlgo::Sha512_256, None) => Box::new(Sha512_256Hasher(Sha512_256::new())),
+        (HashAlgo::Blake3, Some(k)) => {
+            let key_arr: [u8; 32] = k.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "BLAKE3 keyed mode requires a 32-byte key"))?;
+            Box::new(Blake3HasherImpl(blake3::Hasher::new_keyed(&key_arr), opts.length.unwrap_or(32)))
+        }
+        (HashAlgo::Blake3, None) => Box::new(Blake3HasherImpl(blake3::Hasher::new(), opts.length.unwrap_or(32))),
+        (HashAlgo::Blake2b, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "keyed BLAKE2b is not yet supported; use --algo blake3 for keyed mode")),
+        (HashAlgo::Blake2b, None) => Box::new(Blake2bHasherImpl(Blake2bVar::new(opts.length.unwrap_or(64)).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid BLAKE2b output length: {}", e)))?)),
+        (HashAlgo::Crc32, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "CRC32 is not a cryptographic algorithm and does not support --key/--derive-key")),
+        (HashAlgo::Crc32, None) => Box::new(Crc32Hasher(crc32fast::Hasher::new())),
+        (HashAlgo::Xxh3, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "XXH3 is not a cryptographic algorithm and does not support --key/--derive-key")),
+        (HashAlgo::Xxh3, None) => Box::new(Xxh3HasherImpl(xxhash_rust::xxh3::Xxh3::new())),
+        (HashAlgo::Shake128, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "keyed SHAKE128 is not yet supported; use --algo blake3 for keyed mode")),
+        (HashAlgo::Shake128, None) => Box::new(Shake128HasherImpl(Shake128::default(), opts.length.unwrap_or(32))),
+        (HashAlgo::Shake256, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "keyed SHAKE256 is not yet supported; use --algo blake3 for keyed mode")),
+        (HashAlgo::Shake256, None) => Box::new(Shake256HasherImpl(Shake256::default(), opts.length.unwrap_or(64))),
+    })
+}
+
+/// Load `--key` material: a literal hex string, or `@path` to read hex text from a file.
+fn load_key_material(spec: &str) -> io::Result<Vec<u8>> {
+    let hex_str = if let Some(path) = spec.strip_prefix('@') { std::fs::read_to_string(path)?.trim().to_string() } else { spec.to_string() };
+    hex::decode(&hex_str).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid hex key: {}", e)))
+}
+
+/// Resolve `--key`/`--derive-key` into the effective key bytes to hash with.
+/// `--derive-key CONTEXT` runs `blake3::derive_key` over the `--key` material
+/// to produce a context-separated BLAKE3 subkey, so it's only valid with
+/// `--algo blake3`; `--key` alone is used directly. BLAKE3's native keyed mode
+/// requires an exact 32-byte key, so that's checked here rather than in
+/// `hasher()`, which is invoked lazily per file and would otherwise let a
+/// malformed `--key` slip past error-swallowing call sites undetected.
+fn resolve_key(algo: &HashAlgo, key: &Option<String>, derive_key: &Option<String>) -> io::Result<Option<Vec<u8>>> {
+    if key.is_none() && derive_key.is_none() { return Ok(None); }
+    if matches!(algo, HashAlgo::Crc32 | HashAlgo::Xxh3) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not a cryptographic algorithm and cannot be used with --key/--derive-key", algo)));
+    }
+    if matches!(algo, HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("keyed {:?} is not yet supported; use --algo blake3 for keyed mode", algo)));
+    }
+    let material = match key { Some(spec) => load_key_material(spec)?, None => Vec::new() };
+    match derive_key {
+        Some(context) => {
+            if !matches!(algo, HashAlgo::Blake3) { return Err(io::Error::new(io::ErrorKind::InvalidInput, "--derive-key is only supported with --algo blake3")); }
+            Ok(Some(blake3::derive_key(context, &material).to_vec()))
+        }
+        None => {
+            if matches!(algo, HashAlgo::Blake3) && material.len() != 32 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("BLAKE3 keyed mode requires a 32-byte key, got {} bytes", material.len())));
+            }
+            Ok(Some(material))
+        }
+    }
+}
+
+fn resolve_pieces(pieces: Option<u64>) -> io::Result<Option<u64>> {
+    match pieces {
+        Some(0) => Err(io::Error::new(io::ErrorKind::InvalidInput, "--pieces must be greater than zero")),
+        other => Ok(other),
     }
 }
 
-fn hash_file(path: &Path, algo: &HashAlgo) -> io::Result<String> {
+/// Validate `--length` against the chosen algorithm: only BLAKE3, SHAKE128
+/// and SHAKE256 (any positive size) and BLAKE2b (1-64 bytes) support a
+/// non-default digest size.
+fn resolve_length(algo: &HashAlgo, length: Option<usize>) -> io::Result<Option<usize>> {
+    match (algo, length) {
+        (_, None) => Ok(None),
+        (HashAlgo::Blake3 | HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256, Some(0)) => Err(io::Error::new(io::ErrorKind::InvalidInput, "--length must be greater than zero")),
+        (HashAlgo::Blake2b, Some(n)) if n > 64 => Err(io::Error::new(io::ErrorKind::InvalidInput, "BLAKE2b output length must be between 1 and 64 bytes")),
+        (HashAlgo::Blake3 | HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256, Some(n)) => Ok(Some(n)),
+        (other, Some(_)) => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} does not support --length (only blake3, blake2b, shake128 and shake256 do)", other))),
+    }
+}
+
+fn hash_bytes(data: &[u8], algo: &HashAlgo, opts: HashOpts) -> io::Result<String> {
+    let mut h = hasher(algo, opts)?;
+    h.update(data);
+    Ok(h.finalize())
+}
+
+/// Above this size, mmap's page-cache pressure and virtual-memory overhead
+/// outweigh its benefit over a buffered streaming read, so `hash_file` falls
+/// back to `hash_reader` instead of mapping the whole file at once.
+const MMAP_SIZE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+fn hash_file(path: &Path, algo: &HashAlgo, opts: HashOpts) -> io::Result<String> {
     let file = File::open(path)?;
-    if let Ok(map) = unsafe { Mmap::map(&file) } {
-        Ok(hash_bytes(&map, algo))
-    } else {
-        hash_reader(BufReader::new(file), algo)
+    if opts.key.is_none() && file.metadata().map(|m| m.len()).unwrap_or(u64::MAX) <= MMAP_SIZE_THRESHOLD {
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            return hash_bytes(&map, algo, opts);
+        }
     }
+    hash_reader(BufReader::new(file), algo, opts)
 }
 
-fn hash_reader<R: Read>(mut reader: R, algo: &HashAlgo) -> io::Result<String> {
+fn hash_reader<R: Read>(mut reader: R, algo: &HashAlgo, opts: HashOpts) -> io::Result<String> {
     let mut buf = [0u8; 64 * 1024];
-    match algo {
-        HashAlgo::Md5 => { let mut h = Md5::new(); loop { let n = reader.read(&mut buf)?; if n == 0 { break; } h.update(&buf[..n]); } Ok(encode(h.finalize())) }
-        HashAlgo::Sha1 => { let mut h = Sha1::new(); loop { let n = reader.read(&mut buf)?; if n == 0 { break; } h.update(&buf[..n]); } Ok(encode(h.finalize())) }
-        HashAlgo::Sha256 => { let mut h = Sha256::new(); loop { let n = reader.read(&mut buf)?; if n == 0 { break; } h.update(&buf[..n]); } Ok(encode(h.finalize())) }
-        HashAlgo::Sha512 => { let mut h = Sha512::new(); loop { let n = reader.read(&mut buf)?; if n == 0 { break; } h.update(&buf[..n]); } Ok(encode(h.finalize())) }
-        HashAlgo::Blake3 => { let mut h = blake3::Hasher::new(); loop { let n = reader.read(&mut buf)?; if n == 0 { break; } h.update(&buf[..n]); } Ok(h.finalize().to_hex().to_string()) }
+    let mut h = hasher(algo, opts)?;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 { break; }
+        h.update(&buf[..n]);
     }
+    Ok(h.finalize())
 }
 
 fn is_weak_algo(algo: &HashAlgo) -> bool { matches!(algo, HashAlgo::Md5 | HashAlgo::Sha1) }
-fn expected_hex_len(algo: &HashAlgo) -> usize { match algo { HashAlgo::Md5 => 32, HashAlgo::Sha1 => 40, HashAlgo::Sha256 => 64, HashAlgo::Sha512 => 128, HashAlgo::Blake3 => 64 } }
+
+/// Expected hex digest length for an algorithm, accounting for `--length`
+/// on the variable-output algorithms (hex chars = 2 x bytes).
+fn expected_hex_len(algo: &HashAlgo, length: Option<usize>) -> usize {
+    if let (HashAlgo::Blake3 | HashAlgo::Blake2b | HashAlgo::Shake128 | HashAlgo::Shake256, Some(n)) = (algo, length) { return n * 2; }
+    match algo {
+        HashAlgo::Md5 => 32,
+        HashAlgo::Sha1 => 40,
+        HashAlgo::Sha256 => 64,
+        HashAlgo::Sha512 => 128,
+        HashAlgo::Sha3_256 => 64,
+        HashAlgo::Sha3_512 => 128,
+        HashAlgo::Sha512_256 => 64,
+        HashAlgo::Blake3 => 64,
+        HashAlgo::Blake2b => 128,
+        HashAlgo::Crc32 => 8,
+        HashAlgo::Xxh3 => 16,
+        HashAlgo::Shake128 => 64,
+        HashAlgo::Shake256 => 128,
+    }
+}
+
 fn is_valid_hex(s: &str) -> bool { !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit()) }
 fn constant_time_eq(a: &[u8], b: &[u8]) -> bool { let len = a.len().max(b.len()); let mut diff: u8 = 0; for i in 0..len { diff |= a.get(i).copied().unwrap_or(0) ^ b.get(i).copied().unwrap_or(0); } diff == 0 && a.len() == b.len() }
 
-fn process_path(path: &Path, algo: &HashAlgo, exclude_files: &[String], archives: bool, progress: bool) -> io::Result<Vec<HashResult>> {
+/// Hash a file in fixed-size pieces, returning the whole-file hash alongside
+/// one hash per piece. Piece boundaries are enforced by finalizing the
+/// per-piece hasher every `piece_size` bytes and starting a fresh one,
+/// reusing the same 64 KiB read loop as `hash_reader`.
+fn hash_file_pieces(path: &Path, algo: &HashAlgo, piece_size: u64, opts: HashOpts) -> io::Result<(String, Vec<String>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0u8; 64 * 1024];
+    let mut whole = hasher(algo, opts)?;
+    let mut piece = hasher(algo, opts)?;
+    let mut piece_read: u64 = 0;
+    let mut piece_hashes = Vec::new();
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 { break; }
+        let mut offset = 0usize;
+        while offset < n {
+            let take = ((piece_size - piece_read) as usize).min(n - offset);
+            whole.update(&buf[offset..offset + take]);
+            piece.update(&buf[offset..offset + take]);
+            piece_read += take as u64;
+            offset += take;
+            if piece_read == piece_size {
+                piece_hashes.push(piece.finalize());
+                piece = hasher(algo, opts)?;
+                piece_read = 0;
+            }
+        }
+    }
+    if piece_read > 0 { piece_hashes.push(piece.finalize()); }
+    Ok((whole.finalize(), piece_hashes))
+}
+
+/// One cached digest, keyed by canonical path + size + mtime so a changed
+/// file is transparently treated as a cache miss.
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    path: String,
+    len: u64,
+    mtime_nanos: i128,
+    algo: String,
+    hash: String,
+}
+
+type CacheMap = HashMap<(String, u64, i128, String), String>;
+
+/// A `--cache <path>` file's in-memory contents, shared across rayon workers.
+struct HashCache {
+    path: PathBuf,
+    map: Mutex<CacheMap>,
+}
+
+/// Load a `--cache` file if it exists; a missing or unparseable cache just
+/// starts empty rather than failing the whole run.
+fn load_cache(path: &Path) -> HashCache {
+    let map = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<Vec<CacheEntry>>(&data).ok())
+        .map(|entries| entries.into_iter().map(|e| ((e.path, e.len, e.mtime_nanos, e.algo), e.hash)).collect())
+        .unwrap_or_default();
+    HashCache { path: path.to_path_buf(), map: Mutex::new(map) }
+}
+
+/// Flush the in-memory cache back to disk at exit.
+fn save_cache(cache: &HashCache) -> io::Result<()> {
+    let map = cache.map.lock().unwrap();
+    let entries: Vec<CacheEntry> = map
+        .iter()
+        .map(|((path, len, mtime_nanos, algo), hash)| CacheEntry { path: path.clone(), len: *len, mtime_nanos: *mtime_nanos, algo: algo.clone(), hash: hash.clone() })
+        .collect();
+    std::fs::write(&cache.path, serde_json::to_string_pretty(&entries)?)
+}
+
+fn mtime_nanos(meta: &std::fs::Metadata) -> i128 {
+    meta.modified().ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok()).map(|d| d.as_nanos() as i128).unwrap_or(0)
+}
+
+/// Key a cached digest by algorithm, output length and `--key`/`--derive-key`
+/// material, reusing the BSD tag format (`BLAKE2b-256`) so two different
+/// `--length` requests don't collide. The key bytes are folded in (hex-encoded,
+/// since `opts.key` is already the resolved/derived key) so a cache populated
+/// under one `--key` is never replayed for a run with a different key or no
+/// key at all.
+fn cache_algo_key(algo: &HashAlgo, opts: HashOpts) -> String {
+    match opts.key {
+        Some(k) => format!("{}:{}", algo_to_bsd_tag(algo, opts.length), encode(k)),
+        None => algo_to_bsd_tag(algo, opts.length),
+    }
+}
+
+/// `hash_file`, but consulting `cache` first and updating it on a miss.
+fn cached_hash_file(path: &Path, algo: &HashAlgo, opts: HashOpts, cache: Option<&HashCache>) -> io::Result<String> {
+    let Some(cache) = cache else { return hash_file(path, algo, opts); };
+    let meta = std::fs::metadata(path)?;
+    let key = (
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string(),
+        meta.len(),
+        mtime_nanos(&meta),
+        cache_algo_key(algo, opts),
+    );
+    if let Some(hash) = cache.map.lock().unwrap().get(&key).cloned() { return Ok(hash); }
+    let hash = hash_file(path, algo, opts)?;
+    cache.map.lock().unwrap().insert(key, hash.clone());
+    Ok(hash)
+}
+
+/// Hash one file, expanding into `path#piece=K` entries alongside the
+/// whole-file entry when `pieces` is set. Piece hashing bypasses `cache`,
+/// which only covers whole-file digests.
+fn hash_entries(file_path: &Path, algo: &HashAlgo, pieces: Option<u64>, opts: HashOpts, cache: Option<&HashCache>) -> io::Result<Vec<HashResult>> {
+    match pieces {
+        Some(piece_size) => {
+            let (whole, piece_hashes) = hash_file_pieces(file_path, algo, piece_size, opts)?;
+            let mut out = vec![HashResult { path: file_path.display().to_string(), hash: whole }];
+            for (k, hash) in piece_hashes.into_iter().enumerate() {
+                out.push(HashResult { path: format!("{}#piece={}", file_path.display(), k), hash });
+            }
+            Ok(out)
+        }
+        None => Ok(vec![HashResult { path: file_path.display().to_string(), hash: cached_hash_file(file_path, algo, opts, cache)? }]),
+    }
+}
+
+/// `generate`'s walk-time knobs for `process_path`.
+struct ProcessOpts<'a> {
+    archives: bool,
+    progress: bool,
+    pieces: Option<u64>,
+    cache: Option<&'a HashCache>,
+}
+
+/// Build the `--exclude` globset shared by `process_path`, `collect_candidate_files` and `tree_digest`.
+fn build_glob_set(patterns: &[String]) -> io::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pat in patterns { builder.add(Glob::new(pat).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid glob pattern '{}': {}", pat, e)))?); }
+    builder.build().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn process_path(path: &Path, algo: &HashAlgo, exclude_files: &[String], opts: HashOpts, process_opts: ProcessOpts) -> io::Result<Vec<HashResult>> {
+    let ProcessOpts { archives, progress, pieces, cache } = process_opts;
     #[cfg(not(feature = "archives"))] let _ = archives;
     #[cfg(not(feature = "progress"))] let _ = progress;
-    let mut builder = GlobSetBuilder::new();
-    for pat in exclude_files { builder.add(Glob::new(pat).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid glob pattern '{}': {}", pat, e)))?); }
-    let glob_set = builder.build().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let glob_set = build_glob_set(exclude_files)?;
     let mut results = Vec::new();
     if path.is_file() {
-        if !glob_set.is_match(path) { results.push(HashResult { path: path.display().to_string(), hash: hash_file(path, algo)? }); }
+        if !glob_set.is_match(path) { results.extend(hash_entries(path, algo, pieces, opts, cache)?); }
     } else if path.is_dir() {
         let entries: Vec<_> = WalkDir::new(path).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file() && !glob_set.is_match(e.path())).collect();
         #[cfg(feature = "progress")] let pb = if progress { Some(indicatif::ProgressBar::new_spinner()) } else { None };
@@ -191,8 +811,8 @@ fn process_path(path: &Path, algo: &HashAlgo, exclude_files: &[String], archives
         results = entries.par_iter().flat_map_iter(|entry| {
             let file_path = entry.path();
             #[cfg(feature = "progress")] if let Some(ref pb) = pb { pb.set_message(file_path.display().to_string()); }
-            #[cfg(feature = "archives")] if archives { if let Some(ext) = file_path.extension().and_then(|e| e.to_str()).map(|s| s.to_ascii_lowercase()) { if ext == "zip" { return match hash_zip(file_path, algo) { Ok(v) => v, Err(_) => Vec::new() }; } if ext == "tar" { return match hash_tar_like(file_path, algo, false) { Ok(v) => v, Err(_) => Vec::new() }; } if ext == "gz" { let name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_ascii_lowercase(); if name.ends_with(".tar.gz") || name.ends_with(".tgz") { return match hash_tar_like(file_path, algo, true) { Ok(v) => v, Err(_) => Vec::new() }; } } } }
-            match hash_file(file_path, algo) { Ok(hash) => vec![HashResult { path: file_path.display().to_string(), hash }], Err(_) => Vec::new() }
+            #[cfg(feature = "archives")] if archives { if let Some(ext) = file_path.extension().and_then(|e| e.to_str()).map(|s| s.to_ascii_lowercase()) { if ext == "zip" { return match hash_zip(file_path, algo, opts) { Ok(v) => v, Err(_) => Vec::new() }; } if ext == "tar" { return match hash_tar_like(file_path, algo, false, opts) { Ok(v) => v, Err(_) => Vec::new() }; } if ext == "gz" { let name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_ascii_lowercase(); if name.ends_with(".tar.gz") || name.ends_with(".tgz") { return match hash_tar_like(file_path, algo, true, opts) { Ok(v) => v, Err(_) => Vec::new() }; } } } }
+            hash_entries(file_path, algo, pieces, opts, cache).unwrap_or_default()
         }).collect();
         #[cfg(feature = "progress")] if let Some(pb) = pb { pb.finish_and_clear(); }
     }
@@ -201,21 +821,21 @@ fn process_path(path: &Path, algo: &HashAlgo, exclude_files: &[String], archives
 }
 
 #[cfg(feature = "archives")]
-fn hash_zip(path: &Path, algo: &HashAlgo) -> io::Result<Vec<HashResult>> {
+fn hash_zip(path: &Path, algo: &HashAlgo, opts: HashOpts) -> io::Result<Vec<HashResult>> {
     let file = File::open(path)?;
     let mut archive = zip::ZipArchive::new(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     let mut out = Vec::new();
     for i in 0..archive.len() {
         let mut entry = archive.by_index(i).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         if entry.is_dir() { continue; }
-        let hash = hash_reader(&mut io::Read::take(&mut entry, u64::MAX), algo)?;
+        let hash = hash_reader(&mut io::Read::take(&mut entry, u64::MAX), algo, opts)?;
         out.push(HashResult { path: format!("{}!/{}", path.display(), entry.name()), hash });
     }
     Ok(out)
 }
 
 #[cfg(feature = "archives")]
-fn hash_tar_like(path: &Path, algo: &HashAlgo, gz: bool) -> io::Result<Vec<HashResult>> {
+fn hash_tar_like(path: &Path, algo: &HashAlgo, gz: bool, opts: HashOpts) -> io::Result<Vec<HashResult>> {
     let file = File::open(path)?;
     let reader: Box<dyn Read> = if gz { Box::new(flate2::read::GzDecoder::new(file)) } else { Box::new(file) };
     let mut archive = tar::Archive::new(reader);
@@ -223,77 +843,425 @@ fn hash_tar_like(path: &Path, algo: &HashAlgo, gz: bool) -> io::Result<Vec<HashR
     for entry in archive.entries()? {
         let mut entry = entry?;
         if entry.header().entry_type().is_dir() { continue; }
-        let hash = hash_reader(&mut entry, algo)?;
+        let hash = hash_reader(&mut entry, algo, opts)?;
         let inner = entry.path().ok().and_then(|p| p.into_owned().into_os_string().into_string().ok()).unwrap_or_else(|| "<unknown>".to_string());
         out.push(HashResult { path: format!("{}!/{}", path.display(), inner), hash });
     }
     Ok(out)
 }
 
-fn output_results(results: &[HashResult], format: OutputFormat, output_file: Option<&PathBuf>, quiet: bool) -> io::Result<()> {
+#[derive(Serialize)]
+struct DuplicateGroup {
+    hash: String,
+    size: u64,
+    paths: Vec<String>,
+}
+
+/// Walk `dir` (reusing the same exclude globset as `process_path`) and return
+/// every non-excluded regular file at least `min_size` bytes long.
+fn collect_candidate_files(dir: &Path, exclude_files: &[String], min_size: u64) -> io::Result<Vec<(PathBuf, u64)>> {
+    let glob_set = build_glob_set(exclude_files)?;
+    let entries: Vec<_> = WalkDir::new(dir).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file() && !glob_set.is_match(e.path())).collect();
+    Ok(entries.into_iter().filter_map(|e| { let len = e.metadata().ok()?.len(); if len < min_size { None } else { Some((e.path().to_path_buf(), len)) } }).collect())
+}
+
+/// Hash only the first `n` bytes of a file; used as the stage-2 prefilter
+/// before committing to a full `hash_file` read.
+fn partial_hash(path: &Path, algo: &HashAlgo, n: u64, opts: HashOpts) -> io::Result<String> {
+    let file = File::open(path)?;
+    hash_reader(file.take(n), algo, opts)
+}
+
+/// Normalize a relative path to forward-slash form so a tree hashes
+/// identically on Windows and Unix.
+fn normalize_rel_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect::<Vec<_>>().join("/")
+}
+
+/// True if any component of `path` is a dotfile/dotdir.
+fn is_hidden_rel(path: &Path) -> bool {
+    path.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Compute one deterministic digest for an entire directory tree: hash each
+/// file's `relative_path_bytes || 0x00 || file_hash_bytes`, sort the
+/// per-file digests lexicographically so filesystem walk order can't affect
+/// the result, concatenate them, and hash the concatenation once more.
+fn tree_digest(dir: &Path, algo: &HashAlgo, exclude_files: &[String], ignore_hidden: bool, follow_symlinks: bool, opts: HashOpts) -> io::Result<String> {
+    let glob_set = build_glob_set(exclude_files)?;
+    let entries: Vec<_> = WalkDir::new(dir)
+        .follow_links(follow_symlinks)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && !glob_set.is_match(e.path()))
+        .filter(|e| !ignore_hidden || !is_hidden_rel(e.path().strip_prefix(dir).unwrap_or(e.path())))
+        .collect();
+    let mut digests = entries.par_iter().map(|entry| -> io::Result<String> {
+        let rel = normalize_rel_path(entry.path().strip_prefix(dir).unwrap_or(entry.path()));
+        let file_hash = hash_file(entry.path(), algo, opts)?;
+        let mut buf = rel.into_bytes();
+        buf.push(0);
+        buf.extend_from_slice(file_hash.as_bytes());
+        hash_bytes(&buf, algo, opts)
+    }).collect::<io::Result<Vec<_>>>()?;
+    digests.sort();
+    hash_bytes(digests.concat().as_bytes(), algo, opts)
+}
+
+/// Find duplicate files under `dir` via the size -> partial-hash -> full-hash
+/// cascade: each stage only re-examines files that survived the previous one,
+/// so full reads only happen on files that are already likely duplicates.
+fn find_duplicates(dir: &Path, algo: &HashAlgo, exclude_files: &[String], partial_bytes: u64, min_size: u64, opts: HashOpts) -> io::Result<Vec<DuplicateGroup>> {
+    // Stage 1: group by exact size; sizes with only one file can't have duplicates.
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for (path, len) in collect_candidate_files(dir, exclude_files, min_size)? {
+        by_size.entry(len).or_default().push(path);
+    }
+
+    // Stage 2: within each size group larger than `partial_bytes`, regroup by
+    // a cheap partial hash before committing to a full read. Groups whose
+    // files are no bigger than the prefilter window skip straight to stage 3,
+    // since reading the first `partial_bytes` of such a file already reads
+    // all of it, so the prefilter would just be a second full read.
+    // A file that fails to hash (unreadable, incompatible --key/algo, ...) is
+    // reported and dropped from consideration rather than silently excluded,
+    // so a whole-run failure doesn't read as "no duplicates found".
+    let mut partial_candidates: Vec<(u64, PathBuf)> = Vec::new();
+    for (size, paths) in by_size.into_iter().filter(|(_, v)| v.len() > 1) {
+        if size <= partial_bytes {
+            partial_candidates.extend(paths.into_iter().map(|p| (size, p)));
+            continue;
+        }
+        let hashed: Vec<(String, PathBuf)> = paths.par_iter().filter_map(|p| match partial_hash(p, algo, partial_bytes, opts) {
+            Ok(h) => Some((h, p.clone())),
+            Err(e) => { eprintln!("⚠️ {} SKIPPED: {}", p.display(), e); None }
+        }).collect();
+        let mut by_partial: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for (h, p) in hashed { by_partial.entry(h).or_default().push(p); }
+        for (_, group) in by_partial.into_iter().filter(|(_, v)| v.len() > 1) {
+            for p in group { partial_candidates.push((size, p)); }
+        }
+    }
+
+    // Stage 3: only the survivors get a full hash.
+    let hashed: Vec<((u64, String), String)> = partial_candidates.par_iter().filter_map(|(size, p)| match hash_file(p, algo, opts) {
+        Ok(h) => Some(((*size, h), p.display().to_string())),
+        Err(e) => { eprintln!("⚠️ {} SKIPPED: {}", p.display(), e); None }
+    }).collect();
+    let mut by_full: HashMap<(u64, String), Vec<String>> = HashMap::new();
+    for (k, path) in hashed { by_full.entry(k).or_default().push(path); }
+
+    let mut groups: Vec<DuplicateGroup> = by_full.into_iter().filter(|(_, v)| v.len() > 1).map(|((size, hash), mut paths)| { paths.sort(); DuplicateGroup { hash, size, paths } }).collect();
+    groups.sort_by(|a, b| a.hash.cmp(&b.hash));
+    Ok(groups)
+}
+
+fn output_duplicates(groups: &[DuplicateGroup], format: OutputFormat, output_file: Option<&PathBuf>, quiet: bool) -> io::Result<()> {
+    let mut output: Box<dyn Write> = if let Some(file) = output_file { Box::new(File::create(file)?) } else { Box::new(io::stdout()) };
+    match format {
+        OutputFormat::Text | OutputFormat::Sumfile => {
+            if !quiet {
+                for (i, g) in groups.iter().enumerate() {
+                    writeln!(output, "Group {} ({} bytes, {}):", i + 1, g.size, g.hash)?;
+                    for p in &g.paths { writeln!(output, "  {}", p)?; }
+                }
+            }
+        }
+        OutputFormat::Json => { serde_json::to_writer_pretty(&mut output, groups)?; }
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(output);
+            wtr.write_record(["group", "size", "hash", "path"])?;
+            for (i, g) in groups.iter().enumerate() {
+                for p in &g.paths { wtr.write_record([&(i + 1).to_string(), &g.size.to_string(), &g.hash, p])?; }
+            }
+            wtr.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn output_results(results: &[HashResult], format: OutputFormat, output_file: Option<&PathBuf>, quiet: bool, bsd_tag: Option<&str>) -> io::Result<()> {
     let mut output: Box<dyn Write> = if let Some(file) = output_file { Box::new(File::create(file)?) } else { Box::new(io::stdout()) };
     match format {
-        OutputFormat::Text | OutputFormat::Sumfile => { if !quiet { for r in results { writeln!(output, "{}  {}", r.hash, r.path)?; } } }
+        OutputFormat::Text | OutputFormat::Sumfile => {
+            if !quiet {
+                for r in results {
+                    match bsd_tag {
+                        Some(tag) => writeln!(output, "{} ({}) = {}", tag, r.path, r.hash)?,
+                        None => writeln!(output, "{}  {}", r.hash, r.path)?,
+                    }
+                }
+            }
+        }
         OutputFormat::Json => { serde_json::to_writer_pretty(&mut output, results)?; }
         OutputFormat::Csv => { let mut wtr = csv::Writer::from_writer(output); for r in results { wtr.serialize(r)?; } wtr.flush()?; }
     }
     Ok(())
 }
 
-fn verify_hash_file(checksum_file: &Path, algo: &HashAlgo, base_dir: Option<&Path>, allow_absolute: bool, quiet: bool) -> io::Result<i32> {
+/// Split a `path#piece=K` entry produced by `generate --pieces` back into
+/// its base path and piece index.
+fn split_piece_path(path: &str) -> Option<(&str, usize)> {
+    let idx = path.rfind("#piece=")?;
+    let (base, tag) = path.split_at(idx);
+    let index: usize = tag["#piece=".len()..].parse().ok()?;
+    Some((base, index))
+}
+
+/// Re-hash `path` in `piece_size`-byte pieces and report which piece indices
+/// (and therefore which `[K*size, (K+1)*size)` byte ranges) don't match
+/// `expected_pieces`, instead of just declaring the whole file FAILED.
+fn report_piece_mismatches(path: &Path, algo: &HashAlgo, piece_size: u64, expected_pieces: &[(usize, String)], opts: HashOpts) -> io::Result<()> {
+    let (_, actual) = hash_file_pieces(path, algo, piece_size, opts)?;
+    for (index, expected_hash) in expected_pieces {
+        let index = *index;
+        let differs = match actual.get(index) {
+            Some(actual_hash) => !constant_time_eq(actual_hash.as_bytes(), expected_hash.as_bytes()),
+            None => true,
+        };
+        if differs {
+            let start = index as u64 * piece_size;
+            let end = start + piece_size;
+            println!("   piece {} differs (bytes [{}, {}))", index, start, end);
+        }
+    }
+    Ok(())
+}
+
+fn verify_hash_file(checksum_file: &Path, algo: &HashAlgo, opts: HashOpts, verify_opts: VerifyOpts, pieces: Option<u64>) -> io::Result<i32> {
+    let VerifyOpts { base_dir, allow_absolute, quiet, status, strict, allow_weak: _ } = verify_opts;
     let mut rdr = csv::Reader::from_path(checksum_file)?;
-    let mut ok = 0usize; let mut failed = 0usize; let mut missing = 0usize; let mut invalid_path = 0usize; let mut errors = 0usize;
+    let mut ok = 0usize; let mut failed = 0usize; let mut missing = 0usize; let mut invalid_path = 0usize;
+    // Unparseable CSV rows: only a failure under --strict. Real hashing/IO
+    // failures below always count, regardless of --strict.
+    let mut malformed = 0usize;
+    let mut errors = 0usize;
+
+    let mut whole_records = Vec::new();
+    let mut piece_map: std::collections::HashMap<String, Vec<(usize, String)>> = std::collections::HashMap::new();
     for result in rdr.deserialize::<HashResult>() {
         match result {
             Ok(record) => {
-                let raw_path = Path::new(&record.path);
-                if !allow_absolute && raw_path.is_absolute() { invalid_path += 1; if !quiet { eprintln!("❌ absolute path not allowed: {}", raw_path.display()); } continue; }
-                let resolved = if let Some(base) = base_dir { if raw_path.is_absolute() { raw_path.to_path_buf() } else { base.join(raw_path) } } else { raw_path.to_path_buf() };
-                if let Some(base) = base_dir {
-                    let Ok(base_can) = base.canonicalize() else { errors += 1; if !quiet { eprintln!("❌ cannot canonicalize base dir: {}", base.display()); } continue; };
-                    if let Ok(res_can) = resolved.canonicalize() { if !res_can.starts_with(&base_can) { invalid_path += 1; if !quiet { eprintln!("❌ path escapes base dir: {}", resolved.display()); } continue; } }
-                }
-                if resolved.exists() {
-                    match hash_file(&resolved, algo) {
-                        Ok(hash) => { if constant_time_eq(hash.as_bytes(), record.hash.as_bytes()) { ok += 1; if !quiet { println!("✅ {} OK", resolved.display()); } } else { failed += 1; println!("❌ {} FAILED", resolved.display()); } }
-                        Err(e) => { errors += 1; eprintln!("❌ {} ERROR: {}", resolved.display(), e); }
-                    }
-                } else { missing += 1; println!("⚠️ {} MISSING", resolved.display()); }
+                if let Some((base, index)) = split_piece_path(&record.path) { piece_map.entry(base.to_string()).or_default().push((index, record.hash)); }
+                else { whole_records.push(record); }
             }
-            Err(e) => { errors += 1; eprintln!("❌ invalid CSV row: {}", e); }
+            Err(e) => { malformed += 1; if !status { eprintln!("❌ invalid CSV row: {}", e); } }
         }
     }
-    if !quiet { eprintln!("Summary: OK={} FAILED={} MISSING={} INVALID_PATH={} ERROR={}", ok, failed, missing, invalid_path, errors); }
-    Ok(if failed == 0 && missing == 0 && invalid_path == 0 && errors == 0 { 0 } else { 1 })
+    for v in piece_map.values_mut() { v.sort_by_key(|(index, _)| *index); }
+
+    for record in whole_records {
+        let raw_path = Path::new(&record.path);
+        if !allow_absolute && raw_path.is_absolute() { invalid_path += 1; if !status { eprintln!("❌ absolute path not allowed: {}", raw_path.display()); } continue; }
+        let resolved = if let Some(base) = base_dir { if raw_path.is_absolute() { raw_path.to_path_buf() } else { base.join(raw_path) } } else { raw_path.to_path_buf() };
+        if let Some(base) = base_dir {
+            let Ok(base_can) = base.canonicalize() else { errors += 1; if !status { eprintln!("❌ cannot canonicalize base dir: {}", base.display()); } continue; };
+            if let Ok(res_can) = resolved.canonicalize() { if !res_can.starts_with(&base_can) { invalid_path += 1; if !status { eprintln!("❌ path escapes base dir: {}", resolved.display()); } continue; } }
+        }
+        if resolved.exists() {
+            match hash_file(&resolved, algo, opts) {
+                Ok(hash) => {
+                    if constant_time_eq(hash.as_bytes(), record.hash.as_bytes()) { ok += 1; if !status && !quiet { println!("✅ {} OK", resolved.display()); } }
+                    else {
+                        failed += 1;
+                        if !status { println!("❌ {} FAILED", resolved.display()); }
+                        if let (Some(piece_size), Some(expected_pieces)) = (pieces, piece_map.get(&record.path)) {
+                            if let Err(e) = report_piece_mismatches(&resolved, algo, piece_size, expected_pieces, opts) { if !status { eprintln!("❌ {} piece re-check ERROR: {}", resolved.display(), e); } }
+                        }
+                    }
+                }
+                Err(e) => { errors += 1; if !status { eprintln!("❌ {} ERROR: {}", resolved.display(), e); } }
+            }
+        } else { missing += 1; if !status { println!("⚠️ {} MISSING", resolved.display()); } }
+    }
+    if !status && !quiet { eprintln!("Summary: OK={} FAILED={} MISSING={} INVALID_PATH={} ERROR={}", ok, failed, missing, invalid_path, errors + malformed); }
+    let malformed_failure = strict && malformed > 0;
+    Ok(if failed == 0 && missing == 0 && invalid_path == 0 && errors == 0 && !malformed_failure { 0 } else { 1 })
 }
 
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::Generate { file_path, algo, exclude, progress, archives } => {
+        Commands::Generate { file_path, algo, exclude, progress, archives, pieces, tag, cache } => {
             if is_weak_algo(&algo) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", algo); process::exit(2); }
+            let key = match resolve_key(&algo, &cli.key, &cli.derive_key) { Ok(k) => k, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let length = match resolve_length(&algo, cli.length) { Ok(l) => l, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let pieces = match resolve_pieces(pieces) { Ok(p) => p, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            if tag && pieces.is_some() {
+                eprintln!("❌ --tag cannot be combined with --pieces: verify --sumfile has no piece-reconstruction logic, so the #piece=K entries would be unverifiable. Use --pieces without --tag, with --format csv, for `verify --pieces` instead.");
+                process::exit(2);
+            }
+            let opts = HashOpts { key: key.as_deref(), length };
             if progress { eprintln!("Note: --progress requires building with the 'progress' feature. Proceeding without progress."); }
             if archives { eprintln!("Note: --archives requires building with the 'archives' feature. Archive hashing is disabled in this build."); }
+            let hash_cache = cache.as_deref().map(load_cache);
             if let Some(path) = file_path {
-                let results = process_path(&path, &algo, &exclude, archives, progress)?;
-                output_results(&results, cli.format, cli.output.as_ref(), cli.quiet)?;
+                let process_opts = ProcessOpts { archives, progress, pieces, cache: hash_cache.as_ref() };
+                let results = process_path(&path, &algo, &exclude, opts, process_opts)?;
+                let bsd_tag = if tag { Some(algo_to_bsd_tag(&algo, length)) } else { None };
+                output_results(&results, cli.format, cli.output.as_ref(), cli.quiet, bsd_tag.as_deref())?;
+                if let Some(ref hc) = hash_cache { save_cache(hc)?; }
+            } else if pieces.is_some() {
+                eprintln!("❌ --pieces requires a file or directory path, not stdin.");
+                process::exit(2);
             } else {
-                let hash = hash_reader(io::stdin().lock(), &algo)?;
+                let hash = hash_reader(io::stdin().lock(), &algo, opts)?;
                 println!("{}", hash);
             }
         }
         Commands::Compare { input_hash, file_path, algo } => {
             if is_weak_algo(&algo) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", algo); process::exit(2); }
-            let expected_len = expected_hex_len(&algo);
-            if input_hash.len() != expected_len || !is_valid_hex(&input_hash) { eprintln!("Invalid {}-bit hash: expected {} hex chars", expected_len * 4, expected_len); process::exit(2); }
-            let actual_hash = hash_file(&file_path, &algo)?;
+            let key = match resolve_key(&algo, &cli.key, &cli.derive_key) { Ok(k) => k, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let length = match resolve_length(&algo, cli.length) { Ok(l) => l, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let opts = HashOpts { key: key.as_deref(), length };
+            let expected_len = expected_hex_len(&algo, length);
+            if input_hash.len() != expected_len || !is_valid_hex(&input_hash) { eprintln!("Invalid hash: expected {} hex chars", expected_len); process::exit(2); }
+            let actual_hash = hash_file(&file_path, &algo, opts)?;
             if constant_time_eq(actual_hash.as_bytes(), input_hash.as_bytes()) { println!("✅ Hash matches!"); } else { println!("❌ Hash does not match."); println!("Expected: {}", input_hash); println!("Actual:   {}", actual_hash); process::exit(1); }
         }
-        Commands::Verify { checksum_file, algo, base_dir, allow_absolute, sumfile } => {
-            if is_weak_algo(&algo) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", algo); process::exit(2); }
-            let code = if sumfile { verify_sumfile(&checksum_file, &algo, base_dir.as_deref(), allow_absolute, cli.quiet)? } else { verify_hash_file(&checksum_file, &algo, base_dir.as_deref(), allow_absolute, cli.quiet)? };
+        Commands::Verify { checksum_file, algo, base_dir, allow_absolute, sumfile, status, strict, pieces } => {
+            if let Some(ref a) = algo { if is_weak_algo(a) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", a); process::exit(2); } }
+            let resolved_algo = algo.clone().unwrap_or(HashAlgo::Sha256);
+            let key = match resolve_key(&resolved_algo, &cli.key, &cli.derive_key) { Ok(k) => k, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let length = match resolve_length(&resolved_algo, cli.length) { Ok(l) => l, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let opts = HashOpts { key: key.as_deref(), length };
+            let pieces = match resolve_pieces(pieces) { Ok(p) => p, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let verify_opts = VerifyOpts { base_dir: base_dir.as_deref(), allow_absolute, quiet: cli.quiet, status, strict, allow_weak: cli.allow_weak };
+            let code = if sumfile { verify_sumfile(&checksum_file, algo.as_ref(), opts, verify_opts)? } else { verify_hash_file(&checksum_file, &resolved_algo, opts, verify_opts, pieces)? };
             if code != 0 { process::exit(code); }
         }
+        Commands::Digest { dir, algo, exclude, ignore_hidden, follow_symlinks } => {
+            if is_weak_algo(&algo) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", algo); process::exit(2); }
+            let key = match resolve_key(&algo, &cli.key, &cli.derive_key) { Ok(k) => k, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let length = match resolve_length(&algo, cli.length) { Ok(l) => l, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let opts = HashOpts { key: key.as_deref(), length };
+            let hash = tree_digest(&dir, &algo, &exclude, ignore_hidden, follow_symlinks, opts)?;
+            let results = vec![HashResult { path: dir.display().to_string(), hash }];
+            output_results(&results, cli.format, cli.output.as_ref(), cli.quiet, None)?;
+        }
+        Commands::Dedup { dir, algo, exclude, partial_bytes, min_size } => {
+            if is_weak_algo(&algo) && !cli.allow_weak { eprintln!("Refusing to use weak algorithm {:?}. Pass --allow-weak to proceed.", algo); process::exit(2); }
+            let key = match resolve_key(&algo, &cli.key, &cli.derive_key) { Ok(k) => k, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let length = match resolve_length(&algo, cli.length) { Ok(l) => l, Err(e) => { eprintln!("❌ {}", e); process::exit(2); } };
+            let opts = HashOpts { key: key.as_deref(), length };
+            let groups = find_duplicates(&dir, &algo, &exclude, partial_bytes, min_size, opts)?;
+            output_duplicates(&groups, cli.format, cli.output.as_ref(), cli.quiet)?;
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// A fresh, empty scratch directory under the OS temp dir, removed on drop.
+    struct ScratchDir(PathBuf);
+    impl ScratchDir {
+        fn new() -> Self {
+            static COUNTER: AtomicU64 = AtomicU64::new(0);
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("hashcc_test_{}_{}", process::id(), n));
+            std::fs::create_dir_all(&dir).unwrap();
+            ScratchDir(dir)
+        }
+        fn path(&self) -> &Path { &self.0 }
+        fn write(&self, name: &str, contents: &[u8]) -> PathBuf {
+            let p = self.0.join(name);
+            std::fs::write(&p, contents).unwrap();
+            p
+        }
+    }
+    impl Drop for ScratchDir {
+        fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+    }
+
+    #[test]
+    fn parse_bsd_line_accepts_a_tagged_sumfile_entry() {
+        let (tag, path, hash) = parse_bsd_line("SHA256 (dir/file.txt) = abc123").unwrap();
+        assert_eq!(tag, "SHA256");
+        assert_eq!(path, "dir/file.txt");
+        assert_eq!(hash, "abc123");
+    }
+
+    #[test]
+    fn parse_bsd_line_rejects_gnu_style_and_malformed_lines() {
+        assert!(parse_bsd_line("abc123  file.txt").is_none());
+        assert!(parse_bsd_line("SHA256 (file.txt) = ").is_none());
+        assert!(parse_bsd_line("SHA256 (file.txt) = not-hex!").is_none());
+        assert!(parse_bsd_line(" (file.txt) = abc123").is_none());
+    }
+
+    #[test]
+    fn parse_gnu_line_accepts_text_and_binary_mode_markers() {
+        assert_eq!(parse_gnu_line("abc123  file.txt").unwrap(), ("abc123".to_string(), "file.txt".to_string()));
+        assert_eq!(parse_gnu_line("abc123 *file.txt").unwrap(), ("abc123".to_string(), "file.txt".to_string()));
+    }
+
+    #[test]
+    fn parse_gnu_line_rejects_bsd_style_and_malformed_lines() {
+        assert!(parse_gnu_line("SHA256 (file.txt) = abc123").is_none());
+        assert!(parse_gnu_line("abc123").is_none());
+        assert!(parse_gnu_line("not-hex  file.txt").is_none());
+    }
+
+    #[test]
+    fn algo_from_hex_len_resolves_unambiguous_lengths() {
+        assert!(matches!(algo_from_hex_len(32, None), Some(HashAlgo::Md5)));
+        assert!(matches!(algo_from_hex_len(40, None), Some(HashAlgo::Sha1)));
+        assert!(algo_from_hex_len(17, None).is_none());
+    }
+
+    #[test]
+    fn algo_from_hex_len_breaks_64_and_128_char_ties_with_the_hint() {
+        assert!(matches!(algo_from_hex_len(64, None), Some(HashAlgo::Sha256)));
+        assert!(matches!(algo_from_hex_len(64, Some(&HashAlgo::Blake3)), Some(HashAlgo::Blake3)));
+        assert!(matches!(algo_from_hex_len(64, Some(&HashAlgo::Blake2b)), Some(HashAlgo::Blake2b)));
+        assert!(matches!(algo_from_hex_len(128, None), Some(HashAlgo::Sha512)));
+        assert!(matches!(algo_from_hex_len(128, Some(&HashAlgo::Sha3_512)), Some(HashAlgo::Sha3_512)));
+        assert!(matches!(algo_from_hex_len(128, Some(&HashAlgo::Blake2b)), Some(HashAlgo::Blake2b)));
+    }
+
+    #[test]
+    fn split_piece_path_recovers_base_path_and_index() {
+        assert_eq!(split_piece_path("dir/file.bin#piece=3").unwrap(), ("dir/file.bin", 3));
+        assert!(split_piece_path("dir/file.bin").is_none());
+        assert!(split_piece_path("dir/file.bin#piece=nope").is_none());
+    }
+
+    #[test]
+    fn hash_file_pieces_splits_on_exact_boundaries_with_a_short_final_piece() {
+        let scratch = ScratchDir::new();
+        let path = scratch.write("data.bin", b"0123456789");
+        let opts = HashOpts::default();
+        let (whole, pieces) = hash_file_pieces(&path, &HashAlgo::Sha256, 3, opts).unwrap();
+        assert_eq!(pieces.len(), 4);
+        assert_eq!(whole, hash_file(&path, &HashAlgo::Sha256, opts).unwrap());
+        for (i, chunk) in b"0123456789".chunks(3).enumerate() {
+            assert_eq!(pieces[i], hash_bytes(chunk, &HashAlgo::Sha256, opts).unwrap());
+        }
+    }
+
+    #[test]
+    fn tree_digest_is_independent_of_directory_walk_order() {
+        let scratch = ScratchDir::new();
+        scratch.write("b.txt", b"second");
+        scratch.write("a.txt", b"first");
+        std::fs::create_dir_all(scratch.path().join("sub")).unwrap();
+        std::fs::write(scratch.path().join("sub/c.txt"), b"third").unwrap();
+        let opts = HashOpts::default();
+        let digests: Vec<String> = (0..5)
+            .map(|_| tree_digest(scratch.path(), &HashAlgo::Sha256, &[], false, false, opts).unwrap())
+            .collect();
+        assert!(digests.windows(2).all(|w| w[0] == w[1]));
+
+        // The same tree created in the opposite order must hash identically.
+        let reversed = ScratchDir::new();
+        std::fs::create_dir_all(reversed.path().join("sub")).unwrap();
+        std::fs::write(reversed.path().join("sub/c.txt"), b"third").unwrap();
+        reversed.write("a.txt", b"first");
+        reversed.write("b.txt", b"second");
+        assert_eq!(tree_digest(reversed.path(), &HashAlgo::Sha256, &[], false, false, opts).unwrap(), digests[0]);
+    }
+}